@@ -0,0 +1,161 @@
+//! gitignore-style ignore rules, used to keep the traversal in `pool` out of vendored
+//! trees and other directories the user doesn't want matched against.
+
+use regex::Regex;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// One compiled line from a `.gitignore` or `.deepcleanignore` file.
+#[derive(Debug, Clone)]
+struct Rule {
+    /// Directory containing the ignore file this rule came from; patterns are matched
+    /// relative to this directory, per gitignore semantics.
+    base_dir: PathBuf,
+    regex: Regex,
+    /// Source pattern had a trailing `/`: only matches directories.
+    dirs_only: bool,
+    /// `!`-prefixed pattern: re-includes a path excluded by an earlier rule.
+    negate: bool,
+}
+
+/// The accumulated set of ignore rules in effect for a directory, inherited from its
+/// ancestors plus its own `.gitignore`/`.deepcleanignore`.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Whether `target` (a descendant of `root`), or any directory between them, is
+    /// ignored by the `.gitignore`/`.deepcleanignore` rules accumulated along the way down.
+    /// Useful when the caller jumps straight to a directory instead of arriving via a walk
+    /// that prunes ignored subtrees as it descends, e.g. `--watch` reacting to a single
+    /// changed path: checking only `target` itself would miss it being nested inside an
+    /// ignored directory (e.g. `vendor/sub` under a root-level `vendor/`), since gitignore
+    /// patterns like `vendor/` only match a path whose *last* component is `vendor`.
+    pub fn path_is_ignored(root: &Path, target: &Path) -> bool {
+        let mut rules = RuleSet::default().extended_with_dir(root);
+        let Ok(rel) = target.strip_prefix(root) else {
+            return false;
+        };
+        let mut dir = root.to_path_buf();
+        for component in rel.components() {
+            dir.push(component);
+            if rules.is_ignored(&dir, true) {
+                return true;
+            }
+            rules = rules.extended_with_dir(&dir);
+        }
+        false
+    }
+
+    /// Returns `self` extended with any ignore rules found directly in `dir`, for use
+    /// when descending into `dir`'s children.
+    pub fn extended_with_dir(&self, dir: &Path) -> RuleSet {
+        let mut rules = self.rules.clone();
+        for file_name in [".gitignore", ".deepcleanignore"] {
+            if let Ok(contents) = fs::read_to_string(dir.join(file_name)) {
+                rules.extend(parse_ignore_file(dir, &contents));
+            }
+        }
+        RuleSet { rules }
+    }
+
+    /// Whether `path` should be skipped per the accumulated rules. The last matching
+    /// rule wins, as in gitignore.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dirs_only && !is_dir {
+                continue;
+            }
+            let Ok(rel) = path.strip_prefix(&rule.base_dir) else {
+                continue;
+            };
+            if rule.regex.is_match(&rel.to_string_lossy()) {
+                ignored = !rule.negate;
+            }
+        }
+        ignored
+    }
+}
+
+fn parse_ignore_file(base_dir: &Path, contents: &str) -> Vec<Rule> {
+    contents
+        .lines()
+        .filter_map(|line| parse_ignore_line(base_dir, line))
+        .collect()
+}
+
+fn parse_ignore_line(base_dir: &Path, line: &str) -> Option<Rule> {
+    let line = line.trim_end();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let (negate, line) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+
+    let (dirs_only, line) = match line.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, line),
+    };
+
+    // A pattern containing a `/` anywhere but the trailing position is anchored to
+    // base_dir; a pattern with no `/` at all may match at any depth under it.
+    let anchored = line.contains('/');
+    let pattern = line.strip_prefix('/').unwrap_or(line);
+
+    let body = glob_to_regex(pattern);
+    let regex_str = if anchored {
+        format!("^{body}$")
+    } else {
+        format!("^(.*/)?{body}$")
+    };
+
+    let regex = Regex::new(&regex_str).ok()?;
+
+    Some(Rule {
+        base_dir: base_dir.to_path_buf(),
+        regex,
+        dirs_only,
+        negate,
+    })
+}
+
+/// Translates a gitignore glob (`*`, `?`, `**`) into the body of a regex.
+fn glob_to_regex(glob: &str) -> String {
+    let mut out = String::new();
+    let mut chars = glob.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    out.push_str("(.*/)?");
+                } else {
+                    out.push_str(".*");
+                }
+            }
+            '*' => out.push_str("[^/]*"),
+            '?' => out.push_str("[^/]"),
+            c if matches!(
+                c,
+                '.' | '+' | '(' | ')' | '|' | '{' | '}' | '^' | '$' | '\\' | '[' | ']'
+            ) =>
+            {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+
+    out
+}
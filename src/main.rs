@@ -1,10 +1,18 @@
+mod ignore;
+mod pool;
+mod watch;
+
 use regex::Regex;
+use serde::Deserialize;
 use std::{
     fs,
     io::{self, stderr, stdout, Write},
-    os::unix::ffi::OsStrExt,
+    os::unix::{ffi::OsStrExt, process::CommandExt},
     path::{Path, PathBuf},
     process::{exit, Command, ExitStatus, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
 };
 
 fn usage() -> ! {
@@ -12,19 +20,37 @@ fn usage() -> ! {
     let exec_name = std::env::args().next().unwrap();
     indoc::eprintdoc! {"
         Usage: {exec_name} [OPTIONS] DIR
-          -n, --dry-run   Skip running cleanup commands in matched directories.
-                            This may search directories that would've been cleaned up otherwise,
-                            resulting in different matches than normal.
-          -l, --list      Only list information about cleanup patterns.
-          -v, --verbose   Print shell commands being run and their outputs.
-          -h, --help      Show this message
+          -n, --dry-run       Skip running cleanup commands in matched directories.
+                                This may search directories that would've been cleaned up otherwise,
+                                resulting in different matches than normal.
+          -l, --list          Only list information about cleanup patterns.
+          -v, --verbose       Print shell commands being run and their outputs.
+                                With -j > 1, this serializes workers against each other
+                                (a command's own output can't be routed through the
+                                lock that keeps aligned match/result lines from
+                                interleaving), so expect no speedup from -j.
+          -c, --config FILE   Load custom patterns from a TOML file, merged with the builtins.
+                                Defaults to ~/.config/deepclean/patterns.toml if it exists.
+          --no-builtins       Don't include the builtin patterns; use only configured ones.
+          --no-ignore         Don't honor .gitignore/.deepcleanignore files while traversing.
+          -t, --timeout SECS      Kill check/clean commands that run longer than this (default 10).
+          --kill-after SECS       Grace period between SIGTERM and SIGKILL on timeout (default 5).
+          -w, --watch         Instead of a single sweep, watch DIR and clean directories as
+                                build artifacts appear in them. Runs until interrupted.
+          -j, --threads N     Number of worker threads to traverse and clean with (default 1).
+          -h, --help          Show this message
     "};
     exit(1)
 }
 
 fn main() {
-    let args = std::env::args().collect::<Vec<_>>();
+    exit(run(&std::env::args().collect::<Vec<_>>()));
+}
 
+/// The logic formerly inlined in `main`, pulled out so error paths return an exit code
+/// instead of calling `exit` themselves. `usage()` is the one exception: it always wants to
+/// terminate the process immediately, so it keeps calling `exit` and never returns.
+fn run(args: &[String]) -> i32 {
     if args.len() == 1 {
         usage()
     }
@@ -32,61 +58,104 @@ fn main() {
     let mut dry_run = false;
     let mut verbose = false;
     let mut list_pats = false;
-
-    for arg in args.iter().filter(|s| s.starts_with('-')) {
+    let mut no_builtins = false;
+    let mut no_ignore = false;
+    let mut do_watch = false;
+    let mut config_path = None;
+    let mut timeout = Duration::from_secs(10);
+    let mut kill_after = Duration::from_secs(5);
+    let mut n_threads: usize = 1;
+    let mut positional = Vec::new();
+
+    let mut rest_args = args.iter().skip(1);
+    while let Some(arg) = rest_args.next() {
         match arg.as_str() {
             "-n" | "--dry-run" => dry_run = true,
             "-v" | "--verbose" => verbose = true,
             "-l" | "--list" => list_pats = true,
-            _ => usage(),
+            "--no-builtins" => no_builtins = true,
+            "--no-ignore" => no_ignore = true,
+            "-w" | "--watch" => do_watch = true,
+            "-c" | "--config" => {
+                let Some(path) = rest_args.next() else {
+                    usage();
+                };
+                config_path = Some(PathBuf::from(path));
+            }
+            "-t" | "--timeout" => {
+                let Some(secs) = rest_args.next().and_then(|s| s.parse().ok()) else {
+                    usage();
+                };
+                timeout = Duration::from_secs(secs);
+            }
+            "--kill-after" => {
+                let Some(secs) = rest_args.next().and_then(|s| s.parse().ok()) else {
+                    usage();
+                };
+                kill_after = Duration::from_secs(secs);
+            }
+            "-j" | "--threads" => {
+                let Some(n) = rest_args
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .filter(|&n: &usize| n > 0)
+                else {
+                    usage();
+                };
+                n_threads = n;
+            }
+            s if s.starts_with('-') => usage(),
+            s => positional.push(s),
         }
     }
 
-    let rust_proj = Pattern::new("built Rust project")
-        .files_exist(["Cargo.toml"])
-        .dirs_exist(["target"])
-        .clean_commands(["cargo clean"]);
-
-    let makefile_clean_proj = Pattern::new("Makefile with clean target")
-        .files_exist(["Makefile|makefile"])
-        .check_commands(["make clean --dry-run"])
-        .clean_commands(["make clean"]);
-
-    let has_pycache = Pattern::new("contains __pycache__/")
-        .dirs_exist(["__pycache__"])
-        .clean_commands(["rm -r __pycache__"]);
-
-    let has_compiled_pyth = Pattern::new("contains compiled python")
-        .files_exist([r".*\.py[co]"])
-        .clean_commands(["rm *.pyc *.pyo"]);
+    let run_opts = RunOpts {
+        verbose,
+        timeout,
+        kill_after,
+    };
 
-    let git_repo = Pattern::new("git repo")
-        .dirs_exist([".git"])
-        .clean_commands(["git gc --aggressive"]);
+    let mut pats = if no_builtins {
+        Vec::new()
+    } else {
+        builtin_patterns()
+    };
 
-    let ninja_clean_proj = Pattern::new("build.ninja with clean target")
-        .files_exist(["build.ninja"])
-        .check_commands(["ninja clean -n"])
-        .clean_commands(["ninja clean"]);
+    let (config_path, config_explicit) = match config_path {
+        Some(p) => (Some(p), true),
+        None => (default_config_path(), false),
+    };
 
-    let pats = [
-        rust_proj,
-        makefile_clean_proj,
-        has_pycache,
-        has_compiled_pyth,
-        git_repo,
-        ninja_clean_proj,
-    ];
+    if let Some(path) = &config_path {
+        match fs::read_to_string(path) {
+            Ok(toml) => match load_patterns_config(&toml) {
+                Ok(mut custom) => pats.append(&mut custom),
+                Err(e) => {
+                    eprintln!("Loading config `{}`: {e}", path.display());
+                    return 1;
+                }
+            },
+            Err(e) if !config_explicit && e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                eprintln!("Reading config `{}`: {e}", path.display());
+                return 1;
+            }
+        }
+    }
 
     if list_pats {
         println!("{pats:#?}");
-        exit(0);
+        return 0;
+    }
+
+    if pats.is_empty() {
+        eprintln!("No patterns configured (--no-builtins with no --config patterns)");
+        return 1;
     }
 
     let pat_name_align = pats.iter().map(|p| p.name.len()).max().unwrap();
 
-    let mut non_flag_args = args.iter().skip(1).filter(|s| !s.starts_with('-'));
-    let (Some(root_dir), None) = (non_flag_args.next(), non_flag_args.next()) else {
+    let (Some(&root_dir), None) = (positional.first(), positional.get(1)) else {
         usage();
     };
 
@@ -94,71 +163,175 @@ fn main() {
         Ok(meta) => meta,
         Err(e) => {
             eprintln!("Getting metadata for `{root_dir}`: {e}");
-            exit(1);
+            return 1;
         }
     };
 
     if !meta.is_dir() {
         eprintln!("`{root_dir}` is not a directory");
-        exit(1);
+        return 1;
     }
 
     // UNWRAP: Since fs::metadata succeeded earlier, this should succeed
     let root_dir = fs::canonicalize(root_dir).unwrap();
 
-    macro_rules! print_subdir {
-        ($stream_func:expr, $path:expr) => {{
-            let short = match $path.strip_prefix(&root_dir) {
-                Ok(p) if p.as_os_str().is_empty() => root_dir.as_ref(),
-                Ok(p) => p,
-                Err(_) => &$path,
-            };
-            let mut stream = $stream_func();
-            // UNWRAP: These unwraps match std print macro behavior
-            stream.write_all(short.as_os_str().as_bytes()).unwrap();
-            stream.flush().unwrap();
-        }};
+    if do_watch {
+        if let Err(e) = watch::watch(
+            &root_dir,
+            &pats,
+            pat_name_align,
+            run_opts,
+            dry_run,
+            !no_ignore,
+        ) {
+            eprintln!("Watching `{}`: {e}", root_dir.display());
+            return 1;
+        }
+        return 0;
     }
 
-    let mut n_matched = 0;
-    let mut n_cleaned = 0;
-    let mut stk: Vec<PathBuf> = vec![root_dir.clone()];
-    while let Some(dir) = stk.pop() {
-        for pat in &pats {
-            // Check dir for pattern match
-            match pat.match_dir(&dir, verbose) {
-                Ok(false) => continue,
-                Ok(true) => {
-                    n_matched += 1;
-                    print!("* {:pat_name_align$} - ", pat.name);
-                    print_subdir!(stdout, dir);
-                    println!();
-                }
-                Err(e) => {
-                    eprint!("Matching '{}' on `", pat.name);
-                    print_subdir!(stderr, dir);
-                    eprintln!("`: {e}");
-                    break;
-                }
-            }
+    let stats = pool::sweep(
+        root_dir,
+        pats,
+        pat_name_align,
+        run_opts,
+        dry_run,
+        !no_ignore,
+        n_threads,
+    );
+
+    if dry_run {
+        println!(
+            "{} matches, potentially freeing {}",
+            stats.n_matched,
+            human_size(stats.bytes_freed)
+        );
+    } else {
+        println!(
+            "Cleaned {}/{} matches, freed {}",
+            stats.n_cleaned,
+            stats.n_matched,
+            human_size(stats.bytes_freed)
+        );
+    }
 
-            if dry_run {
-                continue;
+    0
+}
+
+/// `path`, relative to `root_dir`, for display (`root_dir` itself if `path` equals it).
+fn subdir_display<'a>(path: &'a Path, root_dir: &'a Path) -> &'a Path {
+    match path.strip_prefix(root_dir) {
+        Ok(p) if p.as_os_str().is_empty() => root_dir,
+        Ok(p) => p,
+        Err(_) => path,
+    }
+}
+
+/// Prints `path` to `stream`, relativized against `root_dir` as by [`subdir_display`].
+fn print_subdir(stream: &mut impl Write, path: &Path, root_dir: &Path) {
+    let short = subdir_display(path, root_dir);
+    // UNWRAP: These unwraps match std print macro behavior
+    stream.write_all(short.as_os_str().as_bytes()).unwrap();
+    stream.flush().unwrap();
+}
+
+/// Tally of what `evaluate_and_clean` did in one directory, for the caller to fold into
+/// its running totals (atomics in the worker pool; `--watch` mode discards it).
+#[derive(Default)]
+struct DirStats {
+    n_matched: usize,
+    n_cleaned: usize,
+    bytes_freed: u64,
+}
+
+/// Output shared by every caller of `evaluate_and_clean`.
+pub(crate) struct Announcer<'a> {
+    pub(crate) root_dir: &'a Path,
+    /// Taken just to print, never across a check/clean subprocess: holding it that long
+    /// would serialize every worker's cleaning on this one lock regardless of `-j`. There are
+    /// two exceptions, in which `evaluate_and_clean` holds it for the whole directory:
+    /// `show_spinner` (a single caller, so there is no other worker to serialize against),
+    /// and `--verbose`, where a subprocess's own inherited stdout/stderr bypasses `printer`
+    /// entirely and would garble across workers no matter how briefly we hold the lock.
+    pub(crate) printer: &'a Mutex<()>,
+    /// Enables the transient "* Cleaning" line between the match announcement and its
+    /// result. Only safe when `show_spinner` is set, which only happens when `self` is the
+    /// sole caller that will ever hold `printer` (single-threaded sweep, or `--watch`'s
+    /// sequential loop): there, nothing else can print between the announcement and the
+    /// result, so the announcement and spinner can be printed ahead of the clean subprocess
+    /// without risking interleaved output. Multi-threaded callers disable it and instead
+    /// print the match line and its result together, in a single lock, after cleaning.
+    pub(crate) show_spinner: bool,
+}
+
+/// Matches `dir` against every pattern and, unless `dry_run`, cleans it for each match.
+/// Shared between the worker pool's top-down sweep and `--watch` mode.
+fn evaluate_and_clean(
+    dir: &Path,
+    pats: &[Pattern],
+    pat_name_align: usize,
+    run_opts: RunOpts,
+    dry_run: bool,
+    out: &Announcer,
+) -> DirStats {
+    let mut stats = DirStats::default();
+    // Computed once per directory, not once per matching pattern: several patterns can
+    // match the same dir (e.g. a Rust project that's also a git repo), and `dir_size` is
+    // already just an estimate of the whole directory rather than what any one pattern's
+    // clean command actually removes, so summing it again per pattern would only compound
+    // the overcount.
+    let mut dir_potential = None;
+
+    // Single-threaded callers (`show_spinner`) never have another worker to race with, and
+    // verbose ones have a subprocess whose own inherited stdout/stderr can't be routed
+    // through `printer` no matter how we slice up our own locking — so in both cases, the
+    // whole directory (every pattern's match_dir through clean_dir) is serialized on one
+    // lock taken up front, rather than released and retaken between patterns.
+    let serialize = out.show_spinner || run_opts.verbose;
+    let _guard = serialize.then(|| out.printer.lock().unwrap());
+
+    for pat in pats {
+        // Check dir for pattern match
+        match pat.match_dir(dir, run_opts) {
+            Ok(false) => continue,
+            Ok(true) => {}
+            Err(e) => {
+                let _guard = (!serialize).then(|| out.printer.lock().unwrap());
+                eprint!("Matching '{}' on `", pat.name);
+                print_subdir(&mut stderr(), dir, out.root_dir);
+                eprintln!("`: {e}");
+                break;
             }
+        }
+
+        stats.n_matched += 1;
+
+        if dry_run {
+            let potential = *dir_potential.get_or_insert_with(|| dir_size(dir));
+            let _guard = (!serialize).then(|| out.printer.lock().unwrap());
+            print!("* {:pat_name_align$} - ", pat.name);
+            print_subdir(&mut stdout(), dir, out.root_dir);
+            println!(" (would free {})", human_size(potential));
+            continue;
+        }
 
-            if !verbose {
+        if serialize {
+            print!("* {:pat_name_align$} - ", pat.name);
+            print_subdir(&mut stdout(), dir, out.root_dir);
+            println!();
+
+            if !run_opts.verbose && out.show_spinner {
                 print!("* Cleaning\r");
                 io::stdout().flush().unwrap();
             }
 
-            // Run the clean commands
-            let err_msg = match pat.clean_dir(&dir, verbose) {
+            let size_before = dir_size(dir);
+            let err_msg = match pat.clean_dir(dir, run_opts) {
                 Ok(true) => {
-                    n_cleaned += 1;
-                    if !verbose {
-                        print!("= Cleaned \r");
-                        io::stdout().flush().unwrap();
-                    }
+                    stats.n_cleaned += 1;
+                    let freed = size_before.saturating_sub(dir_size(dir));
+                    stats.bytes_freed += freed;
+                    println!("= Cleaned (freed {})", human_size(freed));
                     continue;
                 }
                 Ok(false) => "Exit status was non-zero".to_string(),
@@ -166,23 +339,189 @@ fn main() {
             };
 
             eprint!("Clean commands for '{}' in `", pat.name);
-            print_subdir!(stderr, dir);
+            print_subdir(&mut stderr(), dir, out.root_dir);
             eprintln!("`: {err_msg}");
+            continue;
         }
 
-        // UNWRAP: These unwraps will likely work because match_dir would have just checked this,
-        // but this should be handled (TODO)
-        for f in fs::read_dir(&dir).unwrap() {
-            let f = f.unwrap();
-            let ty = f.file_type().unwrap();
-
-            if ty.is_dir() {
-                stk.push(f.path());
+        // Multi-threaded, non-verbose: never hold `printer` across the clean subprocess,
+        // or it'd serialize every worker's actual cleaning on this one lock regardless of
+        // `-j`. Instead print the match line and its outcome together, in a single lock
+        // taken after cleaning finishes, so a concurrent directory's output still can't
+        // land between them.
+        let subdir = subdir_display(dir, out.root_dir).display();
+        let match_line = format!("* {:pat_name_align$} - {subdir}", pat.name);
+        let size_before = dir_size(dir);
+        let clean_result = pat.clean_dir(dir, run_opts);
+
+        let _guard = out.printer.lock().unwrap();
+        println!("{match_line}");
+        match clean_result {
+            Ok(true) => {
+                stats.n_cleaned += 1;
+                let freed = size_before.saturating_sub(dir_size(dir));
+                stats.bytes_freed += freed;
+                println!("= Cleaned (freed {})", human_size(freed));
+            }
+            Ok(false) => {
+                eprintln!(
+                    "Clean commands for '{}' in `{subdir}`: Exit status was non-zero",
+                    pat.name
+                );
+            }
+            Err(e) => {
+                eprintln!("Clean commands for '{}' in `{subdir}`: {e}", pat.name);
             }
         }
     }
 
-    println!("Cleaned {n_cleaned}/{n_matched} matches");
+    if dry_run {
+        stats.bytes_freed += dir_potential.unwrap_or(0);
+    }
+
+    stats
+}
+
+/// Recursively sums the apparent size of everything under `path` (or of `path` itself, if
+/// it's a file). Best-effort: entries that vanish or can't be read mid-walk are skipped
+/// rather than failing, since this is only ever used for reporting.
+fn dir_size(path: &Path) -> u64 {
+    let Ok(meta) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+
+    if !meta.is_dir() {
+        return meta.len();
+    }
+
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries.flatten().map(|entry| dir_size(&entry.path())).sum()
+}
+
+/// Formats a byte count as a human-readable size, e.g. `1.2 GiB`.
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+fn builtin_patterns() -> Vec<Pattern> {
+    let rust_proj = Pattern::new("built Rust project")
+        .files_exist(["Cargo.toml"])
+        .dirs_exist(["target"])
+        .clean_commands(["cargo clean"]);
+
+    let makefile_clean_proj = Pattern::new("Makefile with clean target")
+        .files_exist(["Makefile|makefile"])
+        .check_commands(["make clean --dry-run"])
+        .clean_commands(["make clean"]);
+
+    let has_pycache = Pattern::new("contains __pycache__/")
+        .dirs_exist(["__pycache__"])
+        .clean_commands(["rm -r __pycache__"]);
+
+    let has_compiled_pyth = Pattern::new("contains compiled python")
+        .files_exist([r".*\.py[co]"])
+        .clean_commands(["rm *.pyc *.pyo"]);
+
+    let git_repo = Pattern::new("git repo")
+        .dirs_exist([".git"])
+        .clean_commands(["git gc --aggressive"]);
+
+    let ninja_clean_proj = Pattern::new("build.ninja with clean target")
+        .files_exist(["build.ninja"])
+        .check_commands(["ninja clean -n"])
+        .clean_commands(["ninja clean"]);
+
+    vec![
+        rust_proj,
+        makefile_clean_proj,
+        has_pycache,
+        has_compiled_pyth,
+        git_repo,
+        ninja_clean_proj,
+    ]
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let home = std::env::var_os("HOME")?;
+    Some(Path::new(&home).join(".config/deepclean/patterns.toml"))
+}
+
+/// Deserialized shape of a user-supplied pattern, before its regex fields are compiled.
+#[derive(Debug, Deserialize)]
+struct PatternConfig {
+    name: String,
+    #[serde(default)]
+    files_exist: Vec<String>,
+    #[serde(default)]
+    dirs_exist: Vec<String>,
+    #[serde(default)]
+    check_commands: Vec<String>,
+    #[serde(default)]
+    clean_commands: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PatternsConfig {
+    #[serde(default)]
+    pattern: Vec<PatternConfig>,
+}
+
+fn load_patterns_config(toml: &str) -> Result<Vec<Pattern>, String> {
+    let config: PatternsConfig = toml::from_str(toml).map_err(|e| e.to_string())?;
+    config
+        .pattern
+        .into_iter()
+        .map(pattern_config_to_pattern)
+        .collect()
+}
+
+fn pattern_config_to_pattern(c: PatternConfig) -> Result<Pattern, String> {
+    fn compile_field(
+        pat_name: &str,
+        key: &str,
+        items: Vec<String>,
+    ) -> Result<Box<[Regex]>, String> {
+        items
+            .into_iter()
+            .map(|s| {
+                try_str_to_regex(&s).map_err(|e| {
+                    format!("pattern `{pat_name}`, key `{key}`: compiling regex `{s}`: {e}")
+                })
+            })
+            .collect()
+    }
+
+    if c.files_exist.is_empty() && c.dirs_exist.is_empty() {
+        return Err(format!(
+            "pattern `{}`: at least one of `files_exist`/`dirs_exist` is required, \
+             or it would match every directory in the tree",
+            c.name
+        ));
+    }
+
+    Ok(Pattern {
+        files_exist: compile_field(&c.name, "files_exist", c.files_exist)?,
+        dirs_exist: compile_field(&c.name, "dirs_exist", c.dirs_exist)?,
+        check_commands: c.check_commands.into_iter().map(str_to_string).collect(),
+        clean_commands: c.clean_commands.into_iter().map(str_to_string).collect(),
+        name: c.name.into_boxed_str(),
+    })
 }
 
 #[derive(Default, Clone, Debug)]
@@ -202,7 +541,7 @@ impl Pattern {
         }
     }
 
-    fn match_dir(&self, d: &Path, verbose: bool) -> io::Result<bool> {
+    fn match_dir(&self, d: &Path, opts: RunOpts) -> io::Result<bool> {
         debug_assert!(d.is_absolute(), "match_dir on absolute path");
 
         // Match against files_exist and dirs_exist
@@ -234,7 +573,7 @@ impl Pattern {
 
         // Run check commands
         for c in self.check_commands.iter() {
-            if !run_command(c, d, verbose)?.success() {
+            if !run_command(c, d, opts)?.success() {
                 return Ok(false);
             }
         }
@@ -242,12 +581,12 @@ impl Pattern {
         Ok(true)
     }
 
-    fn clean_dir(&self, d: &Path, verbose: bool) -> io::Result<bool> {
+    fn clean_dir(&self, d: &Path, opts: RunOpts) -> io::Result<bool> {
         debug_assert!(d.is_absolute(), "clean_dir on absolute path");
 
         // Run clean commands
         for c in self.clean_commands.iter() {
-            if !run_command(c, d, verbose)?.success() {
+            if !run_command(c, d, opts)?.success() {
                 return Ok(false);
             }
         }
@@ -256,26 +595,81 @@ impl Pattern {
     }
 }
 
-fn run_command(cmd: &str, dir: &Path, verbose: bool) -> io::Result<ExitStatus> {
-    let mut c = Command::new("timeout");
-    c.args(["--kill-after=5s", "10s", "sh", "-x", "-c", cmd]);
+/// Options threaded down to each subprocess `run_command` spawns.
+#[derive(Clone, Copy, Debug)]
+struct RunOpts {
+    verbose: bool,
+    timeout: Duration,
+    kill_after: Duration,
+}
+
+/// Runs `cmd` in `dir` via `sh -x -c`, in its own process group so the timeout below can
+/// reach any children it forks, not just the `sh` itself.
+fn run_command(cmd: &str, dir: &Path, opts: RunOpts) -> io::Result<ExitStatus> {
+    let mut c = Command::new("sh");
+    c.args(["-x", "-c", cmd]);
     c.current_dir(dir);
 
-    if !verbose {
+    if !opts.verbose {
         c.stdout(Stdio::null());
         c.stderr(Stdio::null());
         c.stdin(Stdio::null());
     }
 
-    let status = c.status()?;
-    if status.code() == Some(124) {
-        Err(io::Error::new(
-            io::ErrorKind::TimedOut,
-            "Command timed out (10s)",
-        ))
-    } else {
-        Ok(status)
+    // SAFETY: setpgid(0, 0) only affects the child process between fork and exec, making
+    // it its own process group leader so -pgid signals reach it and anything it forks.
+    unsafe {
+        c.pre_exec(|| {
+            if libc::setpgid(0, 0) == 0 {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error())
+            }
+        });
     }
+
+    let mut child = c.spawn()?;
+    let pgid = child.id() as libc::pid_t;
+    let start = Instant::now();
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if start.elapsed() >= opts.timeout {
+            break None;
+        }
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    let Some(status) = status else {
+        // SAFETY: kill(2) with no memory involved; pgid is a valid, still-running process
+        // group since try_wait() above hasn't reaped it yet.
+        unsafe { libc::kill(-pgid, libc::SIGTERM) };
+
+        let kill_deadline = Instant::now() + opts.kill_after;
+        let reaped = loop {
+            if child.try_wait()?.is_some() {
+                break true;
+            }
+            if Instant::now() >= kill_deadline {
+                break false;
+            }
+            thread::sleep(Duration::from_millis(50));
+        };
+
+        if !reaped {
+            unsafe { libc::kill(-pgid, libc::SIGKILL) };
+            child.wait()?;
+        }
+
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!("Command timed out ({}s)", opts.timeout.as_secs()),
+        ));
+    };
+
+    Ok(status)
 }
 
 macro_rules! pattern_setters {
@@ -303,6 +697,10 @@ fn str_to_string(s: impl AsRef<str>) -> Box<str> {
 }
 
 fn str_to_regex(s: impl AsRef<str>) -> Regex {
-    let s2 = format!("^({})$", s.as_ref());
-    Regex::new(&s2).unwrap_or_else(|e| panic!("Compiling regex: `{}`\nError: {e}", s.as_ref()))
+    try_str_to_regex(s.as_ref())
+        .unwrap_or_else(|e| panic!("Compiling regex: `{}`\nError: {e}", s.as_ref()))
+}
+
+fn try_str_to_regex(s: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^({s})$"))
 }
@@ -0,0 +1,171 @@
+//! Parallel top-down traversal: a fixed pool of worker threads shares a queue of
+//! directories, each one matching patterns (and cleaning, unless dry-run) before pushing
+//! its own children back onto the queue.
+
+use crate::{evaluate_and_clean, ignore, Announcer, DirStats, Pattern, RunOpts};
+use std::{
+    collections::VecDeque,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+};
+
+struct Shared {
+    root_dir: PathBuf,
+    queue: Mutex<VecDeque<(PathBuf, ignore::RuleSet)>>,
+    queue_cv: Condvar,
+    /// Directories either sitting in the queue or currently being processed by a worker.
+    /// The pool is done once this hits zero with the queue empty.
+    in_flight: AtomicUsize,
+    printer: Mutex<()>,
+    /// Number of worker threads in this pool, so `worker_loop` can tell `evaluate_and_clean`
+    /// whether its non-verbose "Cleaning" spinner is safe to show (only when there's exactly
+    /// one worker to ever hold `printer`).
+    n_threads: usize,
+    n_matched: AtomicUsize,
+    n_cleaned: AtomicUsize,
+    bytes_freed: AtomicU64,
+}
+
+/// Runs the sweep over `root_dir` using `n_threads` workers, returning the aggregate stats.
+pub fn sweep(
+    root_dir: PathBuf,
+    pats: Vec<Pattern>,
+    pat_name_align: usize,
+    run_opts: RunOpts,
+    dry_run: bool,
+    use_ignore: bool,
+    n_threads: usize,
+) -> DirStats {
+    let pats = Arc::new(pats);
+
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::from([(
+            root_dir.clone(),
+            ignore::RuleSet::default(),
+        )])),
+        root_dir,
+        queue_cv: Condvar::new(),
+        in_flight: AtomicUsize::new(1),
+        printer: Mutex::new(()),
+        n_threads,
+        n_matched: AtomicUsize::new(0),
+        n_cleaned: AtomicUsize::new(0),
+        bytes_freed: AtomicU64::new(0),
+    });
+
+    let workers: Vec<_> = (0..n_threads)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            let pats = Arc::clone(&pats);
+            thread::spawn(move || {
+                worker_loop(
+                    &shared,
+                    &pats,
+                    pat_name_align,
+                    run_opts,
+                    dry_run,
+                    use_ignore,
+                )
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        // UNWRAP: workers don't panic on a vanished directory (read_dir failures are
+        // handled in `worker_loop`); a panic here is a real bug worth surfacing loudly.
+        worker.join().unwrap();
+    }
+
+    DirStats {
+        n_matched: shared.n_matched.load(Ordering::SeqCst),
+        n_cleaned: shared.n_cleaned.load(Ordering::SeqCst),
+        bytes_freed: shared.bytes_freed.load(Ordering::SeqCst),
+    }
+}
+
+fn worker_loop(
+    shared: &Shared,
+    pats: &[Pattern],
+    pat_name_align: usize,
+    run_opts: RunOpts,
+    dry_run: bool,
+    use_ignore: bool,
+) {
+    loop {
+        let Some((dir, rules)) = next_dir(shared) else {
+            return;
+        };
+
+        let rules = if use_ignore {
+            rules.extended_with_dir(&dir)
+        } else {
+            rules
+        };
+
+        let out = Announcer {
+            root_dir: &shared.root_dir,
+            printer: &shared.printer,
+            show_spinner: shared.n_threads == 1,
+        };
+        let stats = evaluate_and_clean(&dir, pats, pat_name_align, run_opts, dry_run, &out);
+        shared
+            .n_matched
+            .fetch_add(stats.n_matched, Ordering::SeqCst);
+        shared
+            .n_cleaned
+            .fetch_add(stats.n_cleaned, Ordering::SeqCst);
+        shared
+            .bytes_freed
+            .fetch_add(stats.bytes_freed, Ordering::SeqCst);
+
+        // A clean command can remove the directory it just matched (e.g. a custom pattern
+        // whose clean_commands deletes the whole node_modules/dist dir), so `dir` may no
+        // longer exist by the time we go looking for children: skip it rather than panic.
+        let children: Vec<_> = match fs::read_dir(&dir) {
+            Ok(rd) => rd
+                .flatten()
+                .filter(|f| f.file_type().map(|ty| ty.is_dir()).unwrap_or(false))
+                .map(|f| f.path())
+                .filter(|child| !use_ignore || !rules.is_ignored(child, true))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        if !children.is_empty() {
+            shared.in_flight.fetch_add(children.len(), Ordering::SeqCst);
+            let mut queue = shared.queue.lock().unwrap();
+            queue.extend(children.into_iter().map(|child| (child, rules.clone())));
+            drop(queue);
+            shared.queue_cv.notify_all();
+        }
+
+        // This directory is done; if that drops in-flight to zero with nothing queued,
+        // every worker waiting in `next_dir` wakes up and sees the pool is finished. Unlike
+        // the `fetch_add` above (which can't reach zero, as this directory still counts), the
+        // decrement has to happen under `queue`'s lock: `next_dir` checks `in_flight` and calls `queue_cv.wait` while holding that same
+        // lock, so a decrement-to-zero landing between its check and its wait (if this ran
+        // unlocked) would notify before anyone's listening and strand that worker forever.
+        let queue = shared.queue.lock().unwrap();
+        shared.in_flight.fetch_sub(1, Ordering::SeqCst);
+        drop(queue);
+        shared.queue_cv.notify_all();
+    }
+}
+
+fn next_dir(shared: &Shared) -> Option<(PathBuf, ignore::RuleSet)> {
+    let mut queue = shared.queue.lock().unwrap();
+    loop {
+        if let Some(item) = queue.pop_front() {
+            return Some(item);
+        }
+        if shared.in_flight.load(Ordering::SeqCst) == 0 {
+            return None;
+        }
+        queue = shared.queue_cv.wait(queue).unwrap();
+    }
+}
@@ -0,0 +1,143 @@
+//! `--watch` daemon mode: instead of one top-down sweep, keep watching the tree with
+//! filesystem notifications and re-evaluate directories as they change.
+
+use crate::{evaluate_and_clean, ignore, Announcer, Pattern, RunOpts};
+use notify::{RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    sync::{mpsc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How long to let a burst of filesystem events go quiet before evaluating the directories
+/// they touched. A build writing many files in quick succession collapses into one pass.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+pub fn watch(
+    root_dir: &Path,
+    pats: &[Pattern],
+    pat_name_align: usize,
+    run_opts: RunOpts,
+    dry_run: bool,
+    use_ignore: bool,
+) -> io::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res {
+            let _ = tx.send(event.paths);
+        }
+    })
+    .map_err(to_io_error)?;
+    watcher
+        .watch(root_dir, RecursiveMode::Recursive)
+        .map_err(to_io_error)?;
+
+    println!(
+        "Watching `{}` for changes (Ctrl-C to stop)...",
+        root_dir.display()
+    );
+
+    let printer = Mutex::new(());
+    // Single-threaded here, so the spinner can never be contended by another directory's
+    // clean command.
+    let out = Announcer {
+        root_dir,
+        printer: &printer,
+        show_spinner: true,
+    };
+    let mut suppressed: Vec<(PathBuf, Instant)> = Vec::new();
+
+    loop {
+        // Block for the first event of a batch, then keep draining until it goes quiet.
+        let Ok(first) = rx.recv() else {
+            return Ok(());
+        };
+        let mut changed = first;
+        loop {
+            match rx.recv_timeout(DEBOUNCE) {
+                Ok(more) => changed.extend(more),
+                Err(mpsc::RecvTimeoutError::Timeout) => break,
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+
+        let now = Instant::now();
+        suppressed.retain(|(_, until)| now < *until);
+
+        let mut to_eval: HashSet<PathBuf> = HashSet::new();
+        for path in changed {
+            if suppressed.iter().any(|(dir, _)| path.starts_with(dir)) {
+                continue;
+            }
+
+            let Some(dir) = nearest_matching_ancestor(&path, root_dir, pats, run_opts) else {
+                continue;
+            };
+
+            if use_ignore && ignore::RuleSet::path_is_ignored(root_dir, &dir) {
+                continue;
+            }
+
+            to_eval.insert(dir);
+        }
+
+        let mut cleaned = Vec::new();
+        for dir in to_eval {
+            evaluate_and_clean(&dir, pats, pat_name_align, run_opts, dry_run, &out);
+            if !dry_run {
+                cleaned.push(dir);
+            }
+        }
+
+        // Stamp every suppression deadline from *here*, once the whole batch is done, not
+        // from when each directory's own `evaluate_and_clean` call returned: this loop runs
+        // dirs serially, and a dir's self-generated events only start being drained once we
+        // go back to `rx.recv()` below, which doesn't happen until every dir in the batch is
+        // done. Deadlining an earlier dir off its own finish time would let `now` run past
+        // it while a later dir's clean command is still running, so by the time we're back
+        // around to read events its stale ones (e.g. `git gc --aggressive` touching `.git`
+        // for a `git_repo` pattern, which never stops matching) could slip through and
+        // trigger a needless re-clean. `DEBOUNCE` on top covers the delay before those
+        // events, now queued up, actually get evaluated.
+        let until = Instant::now() + DEBOUNCE;
+        suppressed.extend(cleaned.into_iter().map(|dir| (dir, until)));
+    }
+}
+
+/// Walks up from `path` (or its parent, if `path` isn't itself a directory) looking for the
+/// closest ancestor, no further up than `root_dir`, that matches one of `pats`.
+fn nearest_matching_ancestor(
+    path: &Path,
+    root_dir: &Path,
+    pats: &[Pattern],
+    run_opts: RunOpts,
+) -> Option<PathBuf> {
+    if !path.starts_with(root_dir) {
+        return None;
+    }
+
+    let mut dir = if path.is_dir() {
+        path.to_path_buf()
+    } else {
+        path.parent()?.to_path_buf()
+    };
+
+    loop {
+        if pats
+            .iter()
+            .any(|p| matches!(p.match_dir(&dir, run_opts), Ok(true)))
+        {
+            return Some(dir);
+        }
+        if dir == root_dir {
+            return None;
+        }
+        dir = dir.parent()?.to_path_buf();
+    }
+}
+
+fn to_io_error(e: notify::Error) -> io::Error {
+    io::Error::other(e.to_string())
+}
@@ -0,0 +1,261 @@
+//! End-to-end tests: build a disposable fixture tree under a temp directory, run the
+//! compiled `deepclean` binary against it, and check its exit status, the lines it prints,
+//! and which fixture paths survive the run. Output assertions use regex search rather than
+//! equality so the path-dependent parts (the temp dir's own absolute path) don't need to be
+//! predicted ahead of time.
+
+use regex::Regex;
+use std::{fs, path::Path, process::Command};
+use tempfile::TempDir;
+
+/// Creates `root`/`path` as a directory (if `path` ends with `/`) or a file containing
+/// `contents`, creating any missing parent directories along the way.
+fn write_entry(root: &Path, path: &str, contents: &str) {
+    let full = root.join(path.trim_end_matches('/'));
+    if path.ends_with('/') {
+        fs::create_dir_all(&full).unwrap();
+    } else {
+        fs::create_dir_all(full.parent().unwrap()).unwrap();
+        fs::write(&full, contents).unwrap();
+    }
+}
+
+/// Builds a temp directory containing each `(path, contents)` pair, as described by
+/// [`write_entry`]. The directory is removed when the returned `TempDir` drops.
+fn fixture(entries: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    for (path, contents) in entries {
+        write_entry(dir.path(), path, contents);
+    }
+    dir
+}
+
+/// Runs the built `deepclean` binary with `args`, returning its exit code, stdout, and stderr.
+fn run_deepclean(args: &[&str]) -> (i32, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_deepclean"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap_or(-1),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+/// Asserts each pattern in `patterns` matches somewhere in `text` (unanchored search).
+fn assert_matches_all(text: &str, patterns: &[&str]) {
+    for pattern in patterns {
+        let re = Regex::new(pattern).unwrap();
+        assert!(
+            re.is_match(text),
+            "expected `{pattern}` to match in:\n{text}"
+        );
+    }
+}
+
+/// One end-to-end scenario: build `fixture` under a temp root, run `deepclean` with `args`
+/// against that root, and check the exit status, stdout, and which of `still_exists`/`gone`
+/// (paths relative to the root) are around afterward.
+macro_rules! scenario {
+    (
+        name: $name:ident,
+        fixture: [$($path:expr => $contents:expr),* $(,)?],
+        args: [$($arg:expr),* $(,)?],
+        status: $status:expr,
+        stdout: [$($stdout_pat:expr),* $(,)?],
+        still_exists: [$($keep:expr),* $(,)?],
+        gone: [$($removed:expr),* $(,)?] $(,)?
+    ) => {
+        #[test]
+        fn $name() {
+            let dir = fixture(&[$(($path, $contents)),*]);
+            let root = dir.path().to_str().unwrap();
+            let (status, stdout, _stderr) = run_deepclean(&[$($arg,)* root]);
+
+            assert_eq!(status, $status, "exit status; stdout was:\n{stdout}");
+            assert_matches_all(&stdout, &[$($stdout_pat),*]);
+
+            $(assert!(dir.path().join($keep).exists(), "expected `{}` to still exist", $keep);)*
+            $(assert!(!dir.path().join($removed).exists(), "expected `{}` to be gone", $removed);)*
+        }
+    };
+}
+
+scenario! {
+    name: dry_run_matches_but_cleans_nothing,
+    fixture: [
+        "Cargo.toml" => "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\n",
+        "target/debug/" => "",
+        "target/debug/fixture" => "binary",
+    ],
+    args: ["--dry-run"],
+    status: 0,
+    stdout: [r"built Rust project", r"1 matches, potentially freeing"],
+    still_exists: ["Cargo.toml", "target/debug/fixture"],
+    gone: [],
+}
+
+scenario! {
+    name: dry_run_searches_into_dirs_a_real_run_would_have_removed,
+    fixture: [
+        "Cargo.toml" => "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\n",
+        "target/debug/" => "",
+        "target/__pycache__/mod.cpython-311.pyc" => "",
+    ],
+    args: ["--dry-run"],
+    status: 0,
+    stdout: [r"built Rust project", r"contains __pycache__/", r"contains compiled python", r"3 matches, potentially freeing"],
+    still_exists: ["target/__pycache__/mod.cpython-311.pyc"],
+    gone: [],
+}
+
+scenario! {
+    name: cleans_pycache_dir,
+    fixture: [
+        "__pycache__/mod.cpython-311.pyc" => "",
+        "keep.txt" => "hello",
+    ],
+    args: [],
+    status: 0,
+    stdout: [r"contains __pycache__/", r"Cleaned 1/1 matches, freed"],
+    still_exists: ["keep.txt"],
+    gone: ["__pycache__"],
+}
+
+scenario! {
+    name: ignored_dirs_are_not_matched,
+    fixture: [
+        ".gitignore" => "vendor/\n",
+        "vendor/Cargo.toml" => "[package]\nname = \"vendored\"\nversion = \"0.1.0\"\n",
+        "vendor/target/" => "",
+    ],
+    args: ["--dry-run"],
+    status: 0,
+    stdout: [r"0 matches, potentially freeing 0 B"],
+    still_exists: ["vendor/Cargo.toml", "vendor/target"],
+    gone: [],
+}
+
+scenario! {
+    name: list_prints_patterns_without_touching_the_tree,
+    fixture: ["__pycache__/mod.cpython-311.pyc" => ""],
+    args: ["--list"],
+    status: 0,
+    stdout: [r"built Rust project", r"contains __pycache__/"],
+    still_exists: ["__pycache__/mod.cpython-311.pyc"],
+    gone: [],
+}
+
+scenario! {
+    name: threaded_sweep_cleans_independent_matches,
+    fixture: [
+        "a/__pycache__/x.pyc" => "",
+        "b/c/__pycache__/y.pyc" => "",
+    ],
+    args: ["-j", "4"],
+    status: 0,
+    stdout: [r"Cleaned 2/2 matches, freed"],
+    still_exists: ["a", "b/c"],
+    gone: ["a/__pycache__", "b/c/__pycache__"],
+}
+
+// The remaining scenarios need a `--config` path computed from the fixture's own temp
+// directory, which the `scenario!` macro's static `args` list can't express, so they're
+// written out by hand against the same `fixture`/`run_deepclean`/`assert_matches_all` helpers.
+
+#[test]
+fn no_builtins_without_config_is_a_clean_error_not_a_panic() {
+    let dir = fixture(&[(
+        "Cargo.toml",
+        "[package]\nname = \"fixture\"\nversion = \"0.1.0\"\n",
+    )]);
+    let root = dir.path().to_str().unwrap();
+    let (status, _stdout, stderr) = run_deepclean(&["--no-builtins", root]);
+
+    assert_eq!(status, 1);
+    assert_matches_all(&stderr, &[r"No patterns configured"]);
+}
+
+#[test]
+fn config_patterns_merge_with_builtins() {
+    let dir = fixture(&[
+        ("marker/", ""),
+        (
+            "patterns.toml",
+            indoc::indoc! {r#"
+                [[pattern]]
+                name = "custom marker"
+                dirs_exist = ["marker"]
+                clean_commands = ["rm -r marker"]
+            "#},
+        ),
+    ]);
+    let root = dir.path().to_str().unwrap();
+    let config = dir.path().join("patterns.toml");
+    let config = config.to_str().unwrap();
+    let (status, stdout, _stderr) = run_deepclean(&["--config", config, root]);
+
+    assert_eq!(status, 0, "stdout was:\n{stdout}");
+    assert_matches_all(&stdout, &[r"custom marker", r"Cleaned 1/1 matches, freed"]);
+    assert!(!dir.path().join("marker").exists());
+}
+
+#[test]
+fn config_with_unparseable_regex_reports_error_and_exits_nonzero() {
+    let dir = fixture(&[(
+        "patterns.toml",
+        indoc::indoc! {r#"
+            [[pattern]]
+            name = "bad regex"
+            files_exist = ["(unclosed"]
+        "#},
+    )]);
+    let root = dir.path().to_str().unwrap();
+    let config = dir.path().join("patterns.toml");
+    let config = config.to_str().unwrap();
+    let (status, _stdout, stderr) = run_deepclean(&["--no-builtins", "--config", config, root]);
+
+    assert_eq!(status, 1);
+    assert_matches_all(&stderr, &[r"pattern `bad regex`", r"compiling regex"]);
+}
+
+#[test]
+fn timeout_kills_a_clean_command_that_overruns_its_process_group() {
+    let dir = fixture(&[
+        ("trigger/", ""),
+        (
+            "patterns.toml",
+            indoc::indoc! {r#"
+                [[pattern]]
+                name = "slow clean"
+                dirs_exist = ["trigger"]
+                clean_commands = ["sleep 5"]
+            "#},
+        ),
+    ]);
+    let root = dir.path().to_str().unwrap();
+    let config = dir.path().join("patterns.toml");
+    let config = config.to_str().unwrap();
+
+    let start = std::time::Instant::now();
+    let (status, stdout, stderr) = run_deepclean(&[
+        "--no-builtins",
+        "--config",
+        config,
+        "--timeout",
+        "1",
+        "--kill-after",
+        "1",
+        root,
+    ]);
+    let elapsed = start.elapsed();
+
+    assert_eq!(status, 0, "stdout was:\n{stdout}\nstderr was:\n{stderr}");
+    assert_matches_all(&stdout, &[r"slow clean"]);
+    assert_matches_all(&stderr, &[r"Command timed out"]);
+    assert!(
+        elapsed < std::time::Duration::from_secs(4),
+        "expected the timeout/kill-after pair to cut the 5s sleep short, took {elapsed:?}"
+    );
+}